@@ -0,0 +1,557 @@
+use std::{fmt, error};
+use std::convert::TryInto;
+use sha2::{Sha256, Sha512, Digest};
+use ed25519_dalek::{Keypair, SecretKey, PublicKey};
+use rand::{rngs::OsRng, RngCore};
+use hmac::Hmac;
+use unicode_normalization::UnicodeNormalization;
+use aes::Aes128;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use scrypt::{scrypt, Params as ScryptParams};
+use serde::{Serialize, Deserialize};
+use crate::positive_f64::PositiveF64;
+use crate::transaction::ValidationError;
+
+/// The AES-128 cipher run in CTR mode, used to encrypt the keystore payload.
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+/// The official BIP-39 English word list, one word per line (2048 entries).
+const WORDLIST: &str = include_str!("english.txt");
+
+/// A structure to handle the accounts of the blockchain.
+///
+/// Every account owns an `ed25519_dalek` keypair, used to sign the
+/// transactions, and stores the SHA-512 hash of its password, so that the
+/// plaintext password never has to be kept around.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+	pub name: String,
+	pub surname: String,
+	pub balance: PositiveF64,
+	pub hash_password: [u8; 64],
+	pub keypair: [u8; 64],
+}
+
+impl Account {
+	/// Generates a new `Account`.
+	///
+	/// A fresh `ed25519_dalek` keypair is generated from the operating system's
+	/// random number generator, and the password is stored only as its SHA-512
+	/// hash.
+	pub fn new(name: &str, surname: &str, password: &str) -> Self {
+		let mut csprng = OsRng {};
+
+		let keypair = Keypair::generate(&mut csprng);
+
+		Self {
+			name: name.to_string(),
+			surname: surname.to_string(),
+			balance: PositiveF64(0.0),
+			hash_password: Self::hash_password(password),
+			keypair: keypair.to_bytes(),
+		}
+	}
+
+	/// Returns the account's ed25519 public key, the second half of the stored
+	/// keypair bytes.
+	///
+	/// It identifies the account as a signer, so it can be collected into the
+	/// authorized set a multi-signature transaction is validated against.
+	pub fn public_key(&self) -> [u8; 32] {
+		let mut public_key = [0u8; 32];
+
+		public_key.copy_from_slice(&self.keypair[32..]);
+
+		public_key
+	}
+
+	/// Computes the SHA-512 hash of the given password.
+	pub fn hash_password(password: &str) -> [u8; 64] {
+		let mut hasher = Sha512::new();
+
+		hasher.update(password.as_bytes());
+
+		hasher
+			.finalize()[..]
+			.try_into()
+			.expect("Error generating the SHA-512 hash of the password.")
+	}
+
+	/// Generates a new, random BIP-39 mnemonic of `word_count` words.
+	///
+	/// The allowed word counts are `12`, `15`, `18`, `21` and `24`, each mapping
+	/// to `word_count / 3 * 32` bits of entropy. The last few bits of the
+	/// mnemonic encode an SHA-256 checksum over that entropy, so a phrase
+	/// produced here always validates in `from_mnemonic`.
+	pub fn generate_mnemonic(word_count: usize) -> Result<String, MnemonicError> {
+		let entropy_bits = match word_count {
+			12 | 15 | 18 | 21 | 24 => word_count / 3 * 32,
+			_ => return Err(MnemonicError::InvalidWordCount),
+		};
+
+		let mut entropy = vec![0u8; entropy_bits / 8];
+
+		OsRng {}.fill_bytes(&mut entropy);
+
+		Ok(Self::encode_mnemonic(&entropy))
+	}
+
+	/// Derives the account's keypair deterministically from a BIP-39 mnemonic
+	/// `phrase` and an optional `passphrase`.
+	///
+	/// The phrase is validated against the English word list and its SHA-256
+	/// checksum; the seed is then computed with PBKDF2-HMAC-SHA512 (2048
+	/// iterations, password = the NFKD-normalised mnemonic, salt = `"mnemonic"`
+	/// concatenated with the passphrase), and the first 32 bytes of the 64-byte
+	/// seed are used as the `ed25519_dalek` secret scalar.
+	///
+	/// The resulting account has an empty name and surname and a password equal
+	/// to the passphrase, so it can be recovered from the phrase alone.
+	pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, MnemonicError> {
+		Self::validate_mnemonic(phrase)?;
+
+		let normalized: String = phrase.nfkd().collect();
+		let salt: String = format!("mnemonic{}", passphrase).nfkd().collect();
+
+		let mut seed = [0u8; 64];
+
+		pbkdf2::pbkdf2::<Hmac<Sha512>>(normalized.as_bytes(), salt.as_bytes(), 2048, &mut seed)
+			.map_err(|_| MnemonicError::Pbkdf2)?;
+
+		let secret = SecretKey::from_bytes(&seed[..32]).map_err(|_| MnemonicError::Key)?;
+		let public = PublicKey::from(&secret);
+
+		let keypair = Keypair { secret, public };
+
+		Ok(Self {
+			name: String::new(),
+			surname: String::new(),
+			balance: PositiveF64(0.0),
+			hash_password: Self::hash_password(passphrase),
+			keypair: keypair.to_bytes(),
+		})
+	}
+
+	/// Encodes `entropy` into a BIP-39 mnemonic, appending the SHA-256 checksum
+	/// and mapping each 11-bit group to a word of the English list.
+	fn encode_mnemonic(entropy: &[u8]) -> String {
+		let mut hasher = Sha256::new();
+		hasher.update(entropy);
+		let checksum = hasher.finalize();
+
+		let checksum_bits = entropy.len() * 8 / 32;
+
+		let mut bits = Vec::with_capacity(entropy.len() * 8 + checksum_bits);
+		for byte in entropy {
+			for i in (0..8).rev() {
+				bits.push((byte >> i) & 1 == 1);
+			}
+		}
+		for i in 0..checksum_bits {
+			bits.push((checksum[i / 8] >> (7 - i % 8)) & 1 == 1);
+		}
+
+		let words = Self::wordlist();
+
+		bits.chunks(11)
+			.map(|chunk| {
+				let index = chunk.iter().fold(0usize, |acc, &bit| (acc << 1) | bit as usize);
+				words[index]
+			})
+			.collect::<Vec<_>>()
+			.join(" ")
+	}
+
+	/// Validates a mnemonic `phrase` against the English word list and its
+	/// SHA-256 checksum.
+	fn validate_mnemonic(phrase: &str) -> Result<(), MnemonicError> {
+		let normalized: String = phrase.nfkd().collect();
+		let tokens: Vec<&str> = normalized.split_whitespace().collect();
+
+		if !matches!(tokens.len(), 12 | 15 | 18 | 21 | 24) {
+			return Err(MnemonicError::InvalidWordCount);
+		}
+
+		let words = Self::wordlist();
+
+		let mut bits = Vec::with_capacity(tokens.len() * 11);
+		for token in &tokens {
+			let index = words.iter().position(|word| word == token).ok_or(MnemonicError::UnknownWord)?;
+			for i in (0..11).rev() {
+				bits.push((index >> i) & 1 == 1);
+			}
+		}
+
+		let checksum_bits = bits.len() / 33;
+		let entropy_bits = bits.len() - checksum_bits;
+
+		let mut entropy = vec![0u8; entropy_bits / 8];
+		for (i, bit) in bits[..entropy_bits].iter().enumerate() {
+			if *bit {
+				entropy[i / 8] |= 1 << (7 - i % 8);
+			}
+		}
+
+		let mut hasher = Sha256::new();
+		hasher.update(&entropy);
+		let checksum = hasher.finalize();
+
+		for i in 0..checksum_bits {
+			let expected = (checksum[i / 8] >> (7 - i % 8)) & 1 == 1;
+			if bits[entropy_bits + i] != expected {
+				return Err(MnemonicError::InvalidChecksum);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Saves the account to `path` as a Web3-style v3 JSON keystore, encrypted
+	/// with `password`.
+	///
+	/// A symmetric key is derived from the password with scrypt (the `salt`,
+	/// `n`, `r` and `p` parameters are all stored in the file); the 64-byte
+	/// ed25519 keypair is then encrypted with AES-128-CTR using the first 16
+	/// bytes of the derived key and a random 16-byte IV, and a
+	/// `SHA-512(derived_key[16..32] || ciphertext)` MAC is stored so that a
+	/// wrong password can be detected on load.
+	pub fn save_keystore(&self, path: &str, password: &str) -> std::io::Result<()> {
+		let mut salt = [0u8; 32];
+		let mut iv = [0u8; 16];
+		OsRng {}.fill_bytes(&mut salt);
+		OsRng {}.fill_bytes(&mut iv);
+
+		let params = ScryptParams::recommended();
+		let n = 1u64 << params.log_n();
+
+		let derived_key = Self::scrypt_key(password, &salt, &params);
+
+		let mut ciphertext = self.keypair.to_vec();
+		Aes128Ctr::new((&derived_key[..16]).into(), (&iv[..]).into())
+			.apply_keystream(&mut ciphertext);
+
+		let keystore = Keystore {
+			version: 3,
+			crypto: Crypto {
+				cipher: "aes-128-ctr".to_string(),
+				ciphertext: hex::encode(&ciphertext),
+				cipherparams: CipherParams { iv: hex::encode(iv) },
+				kdf: "scrypt".to_string(),
+				kdfparams: KdfParams {
+					salt: hex::encode(salt),
+					n,
+					r: params.r(),
+					p: params.p(),
+					dklen: 32,
+				},
+				mac: hex::encode(Self::keystore_mac(&derived_key, &ciphertext)),
+			},
+		};
+
+		std::fs::write(path, serde_json::to_string_pretty(&keystore).expect("Error serializing the keystore."))
+	}
+
+	/// Loads an account previously written with `save_keystore` from `path`,
+	/// decrypting it with `password`.
+	///
+	/// The scrypt key is re-derived from the stored parameters and the MAC is
+	/// recomputed; a MAC mismatch surfaces as
+	/// `KeystoreError::Validation(ValidationError::WrongPassword)`. A truncated
+	/// or otherwise corrupt file on disk returns `KeystoreError` rather than
+	/// panicking, so a bad keystore never aborts the process.
+	pub fn load_keystore(path: &str, password: &str) -> Result<Self, KeystoreError> {
+		let contents = std::fs::read_to_string(path)?;
+		let keystore: Keystore = serde_json::from_str(&contents).map_err(|_| KeystoreError::Malformed)?;
+
+		let salt = hex::decode(&keystore.crypto.kdfparams.salt).map_err(|_| KeystoreError::Malformed)?;
+		let iv = hex::decode(&keystore.crypto.cipherparams.iv).map_err(|_| KeystoreError::Malformed)?;
+		if iv.len() != 16 {
+			return Err(KeystoreError::Malformed);
+		}
+		let mut ciphertext = hex::decode(&keystore.crypto.ciphertext).map_err(|_| KeystoreError::Malformed)?;
+
+		let params = ScryptParams::new(
+			(keystore.crypto.kdfparams.n as f64).log2() as u8,
+			keystore.crypto.kdfparams.r,
+			keystore.crypto.kdfparams.p,
+			32,
+		).map_err(|_| KeystoreError::Malformed)?;
+
+		let derived_key = Self::scrypt_key(password, &salt, &params);
+
+		if hex::encode(Self::keystore_mac(&derived_key, &ciphertext)) != keystore.crypto.mac {
+			return Err(KeystoreError::Validation(ValidationError::WrongPassword));
+		}
+
+		Aes128Ctr::new((&derived_key[..16]).into(), (&iv[..]).into())
+			.apply_keystream(&mut ciphertext);
+
+		let keypair: [u8; 64] = ciphertext[..].try_into().map_err(|_| KeystoreError::Malformed)?;
+
+		Ok(Self {
+			name: String::new(),
+			surname: String::new(),
+			balance: PositiveF64(0.0),
+			hash_password: Self::hash_password(password),
+			keypair,
+		})
+	}
+
+	/// Derives a 32-byte key from `password` and `salt` with scrypt.
+	fn scrypt_key(password: &str, salt: &[u8], params: &ScryptParams) -> [u8; 32] {
+		let mut derived_key = [0u8; 32];
+
+		scrypt(password.as_bytes(), salt, params, &mut derived_key)
+			.expect("Error deriving the keystore key with scrypt.");
+
+		derived_key
+	}
+
+	/// Computes the keystore MAC as `SHA-512(derived_key[16..32] || ciphertext)`.
+	fn keystore_mac(derived_key: &[u8; 32], ciphertext: &[u8]) -> [u8; 64] {
+		let mut hasher = Sha512::new();
+
+		hasher.update(&derived_key[16..32]);
+		hasher.update(ciphertext);
+
+		hasher
+			.finalize()[..]
+			.try_into()
+			.expect("Error generating the keystore MAC.")
+	}
+
+	/// Returns the English word list as a vector of 2048 words.
+	fn wordlist() -> Vec<&'static str> {
+		WORDLIST.split_whitespace().collect()
+	}
+
+	/// Adds the given `amount` of money to the account's balance.
+	pub fn add_money(&mut self, amount: f64) {
+		self.balance.0 += amount;
+	}
+}
+
+/// An enum to handle errors generated while deriving an `Account` from a BIP-39
+/// mnemonic.
+#[derive(Debug)]
+pub enum MnemonicError {
+	InvalidWordCount,
+	UnknownWord,
+	InvalidChecksum,
+	Pbkdf2,
+	Key,
+}
+
+impl fmt::Display for MnemonicError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Self::InvalidWordCount => write!(f, "The mnemonic has an invalid number of words."),
+			Self::UnknownWord => write!(f, "The mnemonic contains a word outside the English list."),
+			Self::InvalidChecksum => write!(f, "The mnemonic checksum does not match."),
+			Self::Pbkdf2 => write!(f, "Error deriving the seed with PBKDF2."),
+			Self::Key => write!(f, "Error generating the keypair from the seed."),
+		}
+	}
+}
+
+impl error::Error for MnemonicError {}
+
+/// An enum to handle errors generated while loading an `Account` from a v3 JSON
+/// keystore.
+///
+/// `Io` wraps a failure to read the file, `Malformed` covers a file that cannot
+/// be parsed or decoded (truncated JSON, bad hex, invalid scrypt parameters),
+/// and `Validation` carries the `ValidationError::WrongPassword` raised on a MAC
+/// mismatch.
+#[derive(Debug)]
+pub enum KeystoreError {
+	Io(std::io::Error),
+	Malformed,
+	Validation(ValidationError),
+}
+
+impl fmt::Display for KeystoreError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(error) => write!(f, "Error reading the keystore file: {}", error),
+			Self::Malformed => write!(f, "The keystore file is malformed."),
+			Self::Validation(error) => write!(f, "{}", error),
+		}
+	}
+}
+
+impl error::Error for KeystoreError {}
+
+impl From<std::io::Error> for KeystoreError {
+	fn from(error: std::io::Error) -> Self {
+		Self::Io(error)
+	}
+}
+
+/// The top-level Web3 v3 keystore document.
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+	version: u32,
+	crypto: Crypto,
+}
+
+/// The `crypto` section of the keystore, holding the cipher, KDF and MAC.
+#[derive(Serialize, Deserialize)]
+struct Crypto {
+	cipher: String,
+	ciphertext: String,
+	cipherparams: CipherParams,
+	kdf: String,
+	kdfparams: KdfParams,
+	mac: String,
+}
+
+/// The AES-128-CTR parameters.
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+	iv: String,
+}
+
+/// The scrypt parameters stored so the key can be re-derived on load.
+#[derive(Serialize, Deserialize)]
+struct KdfParams {
+	salt: String,
+	n: u64,
+	r: u32,
+	p: u32,
+	dklen: u32,
+}
+
+impl fmt::Display for Account {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "[{} {}: {}]", self.name, self.surname, self.balance)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn wordlist_has_the_full_canonical_length() {
+		let words = Account::wordlist();
+
+		assert_eq!(words.len(), 2048);
+		assert_eq!(words[0], "abandon");
+		assert_eq!(words[2047], "zoo");
+	}
+
+	#[test]
+	fn generated_mnemonic_round_trips_through_from_mnemonic() {
+		for word_count in [12, 15, 18, 21, 24] {
+			let phrase = Account::generate_mnemonic(word_count).unwrap();
+
+			assert_eq!(phrase.split_whitespace().count(), word_count);
+
+			// A freshly generated phrase must validate and derive a keypair.
+			Account::from_mnemonic(&phrase, "").unwrap();
+		}
+	}
+
+	#[test]
+	fn from_mnemonic_is_deterministic() {
+		let phrase = Account::generate_mnemonic(12).unwrap();
+
+		let first = Account::from_mnemonic(&phrase, "passphrase").unwrap();
+		let second = Account::from_mnemonic(&phrase, "passphrase").unwrap();
+
+		assert_eq!(first.keypair, second.keypair);
+	}
+
+	#[test]
+	fn passphrase_changes_the_derived_keypair() {
+		let phrase = Account::generate_mnemonic(12).unwrap();
+
+		let bare = Account::from_mnemonic(&phrase, "").unwrap();
+		let salted = Account::from_mnemonic(&phrase, "passphrase").unwrap();
+
+		assert_ne!(bare.keypair, salted.keypair);
+	}
+
+	#[test]
+	fn generate_mnemonic_rejects_invalid_word_counts() {
+		assert!(matches!(Account::generate_mnemonic(13), Err(MnemonicError::InvalidWordCount)));
+	}
+
+	#[test]
+	fn from_mnemonic_rejects_an_unknown_word() {
+		let phrase = Account::generate_mnemonic(12).unwrap();
+		let mut tokens: Vec<&str> = phrase.split_whitespace().collect();
+		tokens[0] = "notabip39word";
+
+		assert!(matches!(Account::from_mnemonic(&tokens.join(" "), ""), Err(MnemonicError::UnknownWord)));
+	}
+
+	/// Builds a process-unique path under the temporary directory, avoiding
+	/// collisions between the keystore tests.
+	fn temp_keystore_path(tag: &str) -> String {
+		let mut path = std::env::temp_dir();
+		path.push(format!("rs_crypto_{}_{}.json", tag, std::process::id()));
+		path.to_string_lossy().into_owned()
+	}
+
+	#[test]
+	fn keystore_round_trips_with_the_right_password() {
+		let account = Account::new("a", "a", "secret");
+		let path = temp_keystore_path("roundtrip");
+
+		account.save_keystore(&path, "secret").unwrap();
+		let restored = Account::load_keystore(&path, "secret").unwrap();
+
+		assert_eq!(restored.keypair, account.keypair);
+
+		std::fs::remove_file(&path).ok();
+	}
+
+	#[test]
+	fn keystore_rejects_the_wrong_password() {
+		let account = Account::new("a", "a", "secret");
+		let path = temp_keystore_path("wrongpassword");
+
+		account.save_keystore(&path, "secret").unwrap();
+
+		assert!(matches!(
+			Account::load_keystore(&path, "wrong"),
+			Err(KeystoreError::Validation(ValidationError::WrongPassword)),
+		));
+
+		std::fs::remove_file(&path).ok();
+	}
+
+	#[test]
+	fn loading_a_corrupt_keystore_returns_an_error_instead_of_panicking() {
+		let path = temp_keystore_path("corrupt");
+		std::fs::write(&path, "{ not valid json").unwrap();
+
+		assert!(matches!(Account::load_keystore(&path, "secret"), Err(KeystoreError::Malformed)));
+
+		std::fs::remove_file(&path).ok();
+	}
+
+	#[test]
+	fn loading_a_missing_keystore_returns_an_io_error() {
+		let path = temp_keystore_path("missing");
+		std::fs::remove_file(&path).ok();
+
+		assert!(matches!(Account::load_keystore(&path, "secret"), Err(KeystoreError::Io(_))));
+	}
+
+	#[test]
+	fn from_mnemonic_rejects_a_broken_checksum() {
+		// Flipping the lowest bit of the last word's index changes only a
+		// checksum bit and leaves the entropy intact, so the checksum never matches.
+		let phrase = Account::generate_mnemonic(12).unwrap();
+		let mut tokens: Vec<&str> = phrase.split_whitespace().collect();
+		let last = tokens.len() - 1;
+		let words = Account::wordlist();
+		let index = words.iter().position(|word| *word == tokens[last]).unwrap();
+		tokens[last] = words[index ^ 1];
+
+		assert!(matches!(Account::from_mnemonic(&tokens.join(" "), ""), Err(MnemonicError::InvalidChecksum)));
+	}
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use crate::{
+	account::Account,
+	transaction::{CryptoError, UnverifiedTransaction, ValidationError},
+};
+
+/// An account kept unlocked by the `AccountProvider` until `expiry`.
+///
+/// The plaintext password is cached for the duration of the session, so that
+/// the signing path no longer has to receive it on every call. The `order`
+/// records when the account was unlocked, giving `default_account` a stable
+/// notion of "first" independent of `HashMap` iteration order.
+struct Unlocked {
+	password: String,
+	expiry: DateTime<Utc>,
+	order: u64,
+}
+
+/// A subsystem that holds accounts and lets them be unlocked for a while, so
+/// that transactions can be signed without passing a plaintext password around.
+///
+/// An account is identified by a string `account_id`; once unlocked with its
+/// password it can sign transactions until the unlock expires or `lock` is
+/// called.
+pub struct AccountProvider {
+	accounts: HashMap<String, Account>,
+	unlocked: HashMap<String, Unlocked>,
+	chain_id: u64,
+	next_order: u64,
+}
+
+impl AccountProvider {
+	/// Generates a new, empty `AccountProvider` bound to `chain_id`.
+	pub fn new(chain_id: u64) -> Self {
+		Self {
+			accounts: HashMap::new(),
+			unlocked: HashMap::new(),
+			chain_id,
+			next_order: 0,
+		}
+	}
+
+	/// Adds an account to the provider under the given `account_id`.
+	pub fn add_account(&mut self, account_id: &str, account: Account) {
+		self.accounts.insert(account_id.to_string(), account);
+	}
+
+	/// Unlocks the account identified by `account_id` for the given `duration`,
+	/// verifying `password` against its stored hash.
+	///
+	/// A wrong password (or an unknown account) returns
+	/// `ValidationError::WrongPassword`.
+	pub fn unlock(&mut self, account_id: &str, password: &str, duration: Duration) -> Result<(), ValidationError> {
+		let account = self.accounts.get(account_id).ok_or(ValidationError::WrongPassword)?;
+
+		if account.hash_password != Account::hash_password(password) {
+			return Err(ValidationError::WrongPassword);
+		}
+
+		self.unlocked.insert(account_id.to_string(), Unlocked {
+			password: password.to_string(),
+			expiry: Utc::now() + duration,
+			order: self.next_order,
+		});
+
+		self.next_order += 1;
+
+		Ok(())
+	}
+
+	/// Locks the account identified by `account_id`, dropping its cached unlock.
+	pub fn lock(&mut self, account_id: &str) {
+		self.unlocked.remove(account_id);
+	}
+
+	/// Signs a transaction from the unlocked account `account_id` to `receiver`.
+	///
+	/// The account must currently be unlocked; otherwise
+	/// `ValidationError::NotUnlocked` (wrapped in `CryptoError`) is returned. An
+	/// expired unlock is dropped and treated as locked.
+	///
+	/// Any error raised while building the transaction (a bad key, a hashing
+	/// failure) is propagated as-is, so the real cause is preserved rather than
+	/// being flattened to a single variant.
+	pub fn sign_transaction(&mut self, account_id: &str, receiver: Account, amount: f64) -> Result<UnverifiedTransaction, CryptoError> {
+		let expiry = match self.unlocked.get(account_id) {
+			Some(unlocked) => unlocked.expiry,
+			None => return Err(ValidationError::NotUnlocked.into()),
+		};
+
+		if expiry <= Utc::now() {
+			self.unlocked.remove(account_id);
+
+			return Err(ValidationError::NotUnlocked.into());
+		}
+
+		let password = self.unlocked.get(account_id).expect("The unlock was just checked.").password.clone();
+		let account = self.accounts.get(account_id).ok_or(ValidationError::NotUnlocked)?.clone();
+
+		UnverifiedTransaction::new(account, receiver, amount, &password, self.chain_id)
+	}
+
+	/// Returns the first currently-unlocked account, so callers can omit the
+	/// sender for convenience.
+	///
+	/// "First" is the earliest still-valid unlock by insertion order, so the
+	/// result is deterministic and does not depend on `HashMap` iteration order.
+	pub fn default_account(&self) -> Option<&Account> {
+		self.unlocked
+			.iter()
+			.filter(|(_, unlocked)| unlocked.expiry > Utc::now())
+			.min_by_key(|(_, unlocked)| unlocked.order)
+			.and_then(|(account_id, _)| self.accounts.get(account_id))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Builds a provider holding a single funded account unlockable with the
+	/// password "pw".
+	fn provider_with_account() -> AccountProvider {
+		let mut provider = AccountProvider::new(1);
+		let mut account = Account::new("a", "a", "pw");
+		account.add_money(100.0);
+		provider.add_account("alice", account);
+		provider
+	}
+
+	#[test]
+	fn unlock_rejects_a_wrong_password() {
+		let mut provider = provider_with_account();
+
+		assert!(matches!(provider.unlock("alice", "wrong", Duration::seconds(60)), Err(ValidationError::WrongPassword)));
+	}
+
+	#[test]
+	fn unlock_rejects_an_unknown_account() {
+		let mut provider = provider_with_account();
+
+		assert!(matches!(provider.unlock("bob", "pw", Duration::seconds(60)), Err(ValidationError::WrongPassword)));
+	}
+
+	#[test]
+	fn sign_transaction_succeeds_while_unlocked() {
+		let mut provider = provider_with_account();
+		let receiver = Account::new("b", "b", "b");
+
+		provider.unlock("alice", "pw", Duration::seconds(60)).unwrap();
+
+		assert!(provider.sign_transaction("alice", receiver, 10.0).is_ok());
+	}
+
+	#[test]
+	fn sign_transaction_is_not_unlocked_when_locked() {
+		let mut provider = provider_with_account();
+		let receiver = Account::new("b", "b", "b");
+
+		assert!(matches!(
+			provider.sign_transaction("alice", receiver, 10.0),
+			Err(CryptoError::Validation(ValidationError::NotUnlocked)),
+		));
+	}
+
+	#[test]
+	fn lock_drops_the_cached_unlock() {
+		let mut provider = provider_with_account();
+		let receiver = Account::new("b", "b", "b");
+
+		provider.unlock("alice", "pw", Duration::seconds(60)).unwrap();
+		provider.lock("alice");
+
+		assert!(matches!(
+			provider.sign_transaction("alice", receiver, 10.0),
+			Err(CryptoError::Validation(ValidationError::NotUnlocked)),
+		));
+	}
+
+	#[test]
+	fn an_expired_unlock_is_dropped_and_rejected() {
+		let mut provider = provider_with_account();
+		let receiver = Account::new("b", "b", "b");
+
+		// A zero-length unlock is already expired (`expiry <= Utc::now()`).
+		provider.unlock("alice", "pw", Duration::zero()).unwrap();
+
+		assert!(matches!(
+			provider.sign_transaction("alice", receiver, 10.0),
+			Err(CryptoError::Validation(ValidationError::NotUnlocked)),
+		));
+		assert!(!provider.unlocked.contains_key("alice"));
+	}
+
+	#[test]
+	fn default_account_picks_the_first_unlocked_by_order() {
+		let mut provider = AccountProvider::new(1);
+		provider.add_account("alice", Account::new("alice", "a", "pw"));
+		provider.add_account("bob", Account::new("bob", "b", "pw"));
+
+		provider.unlock("bob", "pw", Duration::seconds(60)).unwrap();
+		provider.unlock("alice", "pw", Duration::seconds(60)).unwrap();
+
+		// Bob was unlocked first, so he is the deterministic default regardless
+		// of `HashMap` iteration order.
+		assert_eq!(provider.default_account().unwrap().name, "bob");
+	}
+}
@@ -0,0 +1,114 @@
+use crate::{
+	account::Account,
+	transaction::{UnverifiedTransaction, VerifiedTransaction, CryptoError},
+};
+
+/// A block of the blockchain, holding a set of validated transactions and, as
+/// `previous_hash`, the hash of the transaction that sealed the previous block
+/// (all zeros for the first block).
+#[derive(Debug, Clone)]
+pub struct Block {
+	pub transactions: Vec<VerifiedTransaction>,
+	pub previous_hash: [u8; 64],
+}
+
+impl Block {
+	/// Generates a new, empty `Block` pointing at `previous_hash`.
+	pub fn new(previous_hash: [u8; 64]) -> Self {
+		Self {
+			transactions: Vec::new(),
+			previous_hash,
+		}
+	}
+}
+
+/// The blockchain itself.
+///
+/// New transactions are accumulated into the current block; once the block
+/// reaches its `capacity`, it is sealed and a new one is started.
+#[derive(Debug, Clone)]
+pub struct BlockChain {
+	pub blocks: Vec<Block>,
+	capacity: usize,
+	chain_id: u64,
+}
+
+impl BlockChain {
+	/// Generates a new `BlockChain` whose blocks hold at most `capacity`
+	/// transactions each, bound to `chain_id`.
+	///
+	/// The `chain_id` is folded into every signed transaction, so a signature
+	/// produced on one chain cannot be replayed on another.
+	pub fn new(capacity: usize, chain_id: u64) -> Self {
+		Self {
+			blocks: vec![Block::new([0; 64])],
+			capacity,
+			chain_id,
+		}
+	}
+
+	/// Performs a new transaction between `sender` and `receiver`, and appends
+	/// it to the current block after a successful validation.
+	///
+	/// A bad key or a tampered account surfaces as a `CryptoError` propagated to
+	/// the caller, leaving the balances untouched.
+	pub fn push_transaction(&mut self, sender: &mut Account, receiver: &mut Account, amount: f64, sender_password: &str) -> Result<(), CryptoError> {
+		let authorized = [sender.public_key()];
+
+		let transaction = UnverifiedTransaction::new(sender.clone(), receiver.clone(), amount, sender_password, self.chain_id)?;
+
+		let hash = transaction.hash;
+		let verified = transaction.validate(hash, self.chain_id, &authorized, 1)?;
+
+		sender.balance.0 -= amount;
+		receiver.add_money(amount);
+
+		self.append(verified);
+
+		Ok(())
+	}
+
+	/// Performs a new multi-signature transaction and appends it to the current
+	/// block after a successful validation.
+	///
+	/// The `signers` are a heterogeneous set of accounts that each sign the same
+	/// transaction (the first is the sender whose balance is drawn on); the
+	/// transaction is accepted only if at least `threshold` of their public keys
+	/// produced a valid signature. The authorized set is taken from the signers
+	/// themselves rather than from the transaction, so it cannot be forged.
+	///
+	/// This convenience path therefore trusts its caller's `signers` list as the
+	/// authorized set — `threshold` is the only gate it enforces. Callers needing
+	/// an authorized set distinct from the signers should build the transaction
+	/// and call [`UnverifiedTransaction::validate`] directly, which is fully
+	/// parameterized over the authorized keys.
+	pub fn push_multisig_transaction(&mut self, signers: &mut [&mut Account], receiver: &mut Account, amount: f64, sender_password: &str, threshold: usize) -> Result<(), CryptoError> {
+		let authorized: Vec<[u8; 32]> = signers.iter().map(|signer| signer.public_key()).collect();
+
+		let transaction = UnverifiedTransaction::new_multisig(signers, receiver.clone(), amount, sender_password, self.chain_id)?;
+
+		let hash = transaction.hash;
+		let verified = transaction.validate(hash, self.chain_id, &authorized, threshold)?;
+
+		signers[0].balance.0 -= amount;
+		receiver.add_money(amount);
+
+		self.append(verified);
+
+		Ok(())
+	}
+
+	/// Appends a validated transaction to the current block, sealing it and
+	/// starting a new one when the capacity is reached.
+	fn append(&mut self, transaction: VerifiedTransaction) {
+		let previous_hash = transaction.hash();
+
+		let block = self.blocks.last_mut().expect("The blockchain always has a block.");
+
+		block.transactions.push(transaction);
+
+		if block.transactions.len() >= self.capacity {
+			self.blocks.push(Block::new(previous_hash));
+		}
+	}
+}
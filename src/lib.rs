@@ -0,0 +1,5 @@
+pub mod account;
+pub mod account_provider;
+pub mod blockchain;
+pub mod positive_f64;
+pub mod transaction;
@@ -14,9 +14,13 @@ fn main() {
     a2.add_money(100.0);
     a4.add_money(100.0);
 
-    let mut blockchain = BlockChain::new(2);
-    blockchain.push_transaction(&mut a0, &mut a1, 2.0, "a");
-    blockchain.push_transaction(&mut a2, &mut a3, 1.0, "c");
+    let mut blockchain = BlockChain::new(2, 1);
+    if let Err(error) = blockchain.push_transaction(&mut a0, &mut a1, 2.0, "a") {
+        eprintln!("{}", error);
+    }
+    if let Err(error) = blockchain.push_transaction(&mut a2, &mut a3, 1.0, "c") {
+        eprintln!("{}", error);
+    }
 
     println!("{} {} {} {} {}", a0, a1, a2, a3, a4);
 }
@@ -0,0 +1,39 @@
+use std::{fmt, error};
+
+/// A wrapper around `f64` that is guaranteed to hold a non-negative value.
+///
+/// It is used by the blockchain to represent balances and amounts, so that an
+/// invalid (negative) quantity can never be constructed in the first place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositiveF64(pub f64);
+
+impl PositiveF64 {
+	/// Generates a new `PositiveF64`.
+	///
+	/// If the given `value` is negative, a `PositiveF64Error` is returned.
+	pub fn new(value: f64) -> Result<Self, PositiveF64Error> {
+		if value < 0.0 {
+			Err(PositiveF64Error)
+		} else {
+			Ok(Self(value))
+		}
+	}
+}
+
+impl fmt::Display for PositiveF64 {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+/// An error returned when a `PositiveF64` is built from a negative value.
+#[derive(Debug)]
+pub struct PositiveF64Error;
+
+impl fmt::Display for PositiveF64Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "A PositiveF64 cannot hold a negative value.")
+	}
+}
+
+impl error::Error for PositiveF64Error {}
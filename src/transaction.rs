@@ -1,5 +1,6 @@
 use std::{fmt, error};
 use std::convert::{TryInto, TryFrom};
+use std::array::TryFromSliceError;
 use sha2::{Sha512, Digest};
 use chrono::{DateTime, Utc};
 use crate::{
@@ -8,25 +9,66 @@ use crate::{
 };
 use ed25519_dalek::{
 	Keypair,
+	PublicKey,
 	Signature,
+	SignatureError,
 	Signer,
+	Verifier,
 };
 
-/// A structure to handle the transactions of the blockchain.
+/// A transaction as received, before its signature and hash have been checked.
+///
+/// It is the only constructible transaction type; it becomes a
+/// [`VerifiedTransaction`] — the only kind the blockchain accepts — solely by
+/// passing through [`UnverifiedTransaction::validate`].
 #[derive(Debug, Clone, PartialEq)]
-pub struct Transaction {
+pub struct UnverifiedTransaction {
 	pub sender: Account,
 	pub receiver: Account,
 	pub amount: f64,
 	pub time: DateTime<Utc>,
+	pub chain_id: u64,
 	hash_sender_password: [u8; 64],
 	message: String,
-	signature: [u8; 64],
+	signatures: Vec<([u8; 32], [u8; 64])>,
 	pub hash: [u8; 64],
 }
 
-impl Transaction {
-	/// Generates a new `Transaction`.
+/// A transaction whose hash, password, signature and amount have all been
+/// verified.
+///
+/// It cannot be built directly: the only way to obtain one is a successful
+/// [`UnverifiedTransaction::validate`], so the type system guarantees that a
+/// blockchain block only ever stores checked transactions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedTransaction {
+	inner: UnverifiedTransaction,
+}
+
+impl VerifiedTransaction {
+	/// The SHA-512 hash of the verified transaction.
+	pub fn hash(&self) -> [u8; 64] {
+		self.inner.hash
+	}
+
+	/// The sender's account.
+	pub fn sender(&self) -> &Account {
+		&self.inner.sender
+	}
+
+	/// The receiver's account.
+	pub fn receiver(&self) -> &Account {
+		&self.inner.receiver
+	}
+
+	/// The amount of the transaction.
+	pub fn amount(&self) -> f64 {
+		self.inner.amount
+	}
+}
+
+impl UnverifiedTransaction {
+	/// Generates a new `UnverifiedTransaction`.
 	/// In order to perform a new transaction, the sender must specify his account, his password,
 	/// the amount and the receiver's account.
 	/// 
@@ -35,36 +77,60 @@ impl Transaction {
 	/// - the receiver's `Account`
 	/// - the amount of the transaction
 	/// - the `DateTime<Utc>` time when the block was generated
+	/// - the `chain_id` the transaction is bound to
 	/// - the SHA-512 hash of the sender's password
 	/// - the message to be signed
-	/// - the digital signature of the message
+	/// - the digital signatures of the message, one per signer
 	/// - the hash of the transaction
-	pub fn new(sender: Account, receiver: Account, amount: f64, sender_password: &str) -> Self {
+	///
+	/// This is the convenience single-signer path (a `1`-of-`1` multisig); use
+	/// [`UnverifiedTransaction::new_multisig`] for transactions that require
+	/// more than one signer.
+	pub fn new(mut sender: Account, receiver: Account, amount: f64, sender_password: &str, chain_id: u64) -> Result<Self, CryptoError> {
+		Self::new_multisig(&[&mut sender], receiver, amount, sender_password, chain_id)
+	}
+
+	/// Generates a new `UnverifiedTransaction` signed by a heterogeneous set of
+	/// `signers`.
+	///
+	/// The first signer is taken as the sender whose balance the transaction
+	/// draws on and whose password is recorded. Every signer produces one
+	/// `[u8; 64]` signature over the same canonical message, stored alongside
+	/// its public key, so a shared or escrow account can require several
+	/// approvals before the transaction becomes valid.
+	///
+	/// The `m`-of-`n` threshold and the set of authorized signers are not stored
+	/// on the transaction — they are policy supplied by the verifier at
+	/// [`UnverifiedTransaction::validate`] time, so a forged transaction cannot
+	/// lower its own acceptance bar.
+	pub fn new_multisig(signers: &[&mut Account], receiver: Account, amount: f64, sender_password: &str, chain_id: u64) -> Result<Self, CryptoError> {
+		let sender = (*signers.first().expect("A transaction needs at least one signer.")).clone();
+
 		let mut hasher = Sha512::new();
 
 		hasher.update(sender_password.as_bytes());
 
 		let hash_sender_password = hasher
 			.finalize()[..]
-			.try_into()
-			.expect("Error generating the SHA-512 hash of the password.");
+			.try_into()?;
 
 		let mut transaction = Self {
 			sender,
 			receiver,
 			amount,
 			time: Utc::now(),
+			chain_id,
 			hash_sender_password,
 			message: String::new(),
-			signature: [0; 64],
+			signatures: Vec::with_capacity(signers.len()),
 			hash: [0; 64],
 		};
 
-		transaction.sign();
+		transaction.sign(signers)?;
 
-		transaction.calculate_hash();
+		transaction.calculate_hash()?;
 
-		transaction
+		Ok(transaction)
 	}
 
 	/// This method is called when a new transaction is generated,
@@ -78,12 +144,30 @@ impl Transaction {
 	/// - the receiver's `Account`
 	/// - the amount of the transaction
 	/// - the `DateTime<Utc>` time when the block was generated
-	fn sign(&mut self) {
-		let keypair = Keypair::from_bytes(&self.sender.keypair).expect("Error generating the Keypair while signing the transaction.");
-		
-		self.message = format!("{}{}{}{:?}", self.sender, self.receiver, self.amount, self.time);
+	/// - the `chain_id` the transaction is bound to
+	///
+	/// One signature per signer is collected over the same canonical message
+	/// and stored together with the signer's public key.
+	fn sign(&mut self, signers: &[&mut Account]) -> Result<(), CryptoError> {
+		self.message = Self::message(&self.sender, &self.receiver, self.amount, &self.time, self.chain_id);
+
+		for signer in signers {
+			let keypair = Keypair::from_bytes(&signer.keypair)?;
 
-		self.signature = keypair.sign(self.message.as_bytes()).to_bytes();
+			let signature = keypair.try_sign(self.message.as_bytes())?.to_bytes();
+
+			self.signatures.push((keypair.public.to_bytes(), signature));
+		}
+
+		Ok(())
+	}
+
+	/// Builds the canonical message that is signed and verified.
+	///
+	/// The `chain_id` is folded in (mirroring EIP-155) so that a signature is
+	/// bound to a single chain and cannot be replayed against another.
+	fn message(sender: &Account, receiver: &Account, amount: f64, time: &DateTime<Utc>, chain_id: u64) -> String {
+		format!("{}{}{}{:?}{}", sender, receiver, amount, time, chain_id)
 	}
 
 	/// This method is called when a new transacion is generated,
@@ -91,17 +175,18 @@ impl Transaction {
 	///
 	/// The hash is calculated by using the `message` and the `signature`,
 	/// both fields generated in the `sign()` method.
-	fn calculate_hash(&mut self) {
+	fn calculate_hash(&mut self) -> Result<(), CryptoError> {
 		let mut hasher = Sha512::new();
 
-		let message = format!("{:?}{:?}", self.message, self.signature);
+		let message = format!("{:?}{:?}", self.message, self.signatures);
 
 		hasher.update(message.as_bytes());
 
 		self.hash = hasher
 			.finalize()[..]
-			.try_into()
-			.expect("Error generating the SHA-512 hash of the transaction.");
+			.try_into()?;
+
+		Ok(())
 	}
 
 	/// This method checks if the transaction is valid,
@@ -111,27 +196,72 @@ impl Transaction {
 	/// a `ValidationError::Tempered` error is returned.
 	/// - If the hash of the sender's password doesn't match with the `hash_sender_password` field,
 	/// a `ValidationError::WrongPassword` error is returned.
-	/// - If the signature verification doesn't succeed,
-	/// a `ValidationError::InvalidSign` error is returned.
+	/// - If fewer than `threshold` distinct public keys drawn from `authorized`
+	/// produced a valid signature, a `ValidationError::InvalidSignature` error is
+	/// returned.
 	/// - If the amount is zero or negative,
-	/// a `ValidationError::InvalidAmount` error is returned. 
-	pub fn validate(&self, hash: [u8; 64]) -> Result<(), ValidationError> {
-		let signature = Signature::try_from(self.signature).expect("Error generating the Signature while validating the transaction.");
-
-		let keypair = Keypair::from_bytes(&self.sender.keypair).expect("Error generating the Keypair while validating the transaction.");
+	/// a `ValidationError::InvalidAmount` error is returned.
+	///
+	/// On success the `UnverifiedTransaction` is consumed and the corresponding
+	/// `VerifiedTransaction` is returned, so the check cannot be bypassed.
+	///
+	/// Both the `authorized` signer set and the `threshold` are supplied by the
+	/// verifier rather than read off the (untrusted) transaction, so a forged
+	/// transaction cannot authorize itself with attacker-controlled keys or a
+	/// lowered threshold.
+	///
+	/// The message is recomputed from the `chain_id` the blockchain expects,
+	/// rather than trusting the stored one, so a transaction minted for a
+	/// different chain fails the signature check with
+	/// `ValidationError::InvalidSignature`.
+	pub fn validate(self, hash: [u8; 64], chain_id: u64, authorized: &[[u8; 32]], threshold: usize) -> Result<VerifiedTransaction, ValidationError> {
+		let message = Self::message(&self.sender, &self.receiver, self.amount, &self.time, chain_id);
 
 		if hash != self.hash {
 			Err(ValidationError::Tempered)
 		} else if self.hash_sender_password != self.sender.hash_password {
 			Err(ValidationError::WrongPassword)
-		} else if keypair.verify(self.message.as_bytes(), &signature).is_err() {
+		} else if self.count_valid_signers(message.as_bytes(), authorized) < threshold {
 			Err(ValidationError::InvalidSignature)
 		} else if PositiveF64::new(self.amount).is_err() || self.amount == 0.0 || self.sender.balance.0 < self.amount {
 			Err(ValidationError::InvalidAmount)
 		} else {
-			Ok(())
+			Ok(VerifiedTransaction { inner: self })
 		}
 	}
+
+	/// Counts how many distinct `authorized` public keys produced a valid
+	/// signature over `message`.
+	///
+	/// A signature only counts when its public key belongs to the `authorized`
+	/// set, and each public key is considered only once even if it signed
+	/// several times, so the count reflects the number of distinct authorized
+	/// signers that approved the transaction.
+	fn count_valid_signers(&self, message: &[u8], authorized: &[[u8; 32]]) -> usize {
+		let mut seen: Vec<[u8; 32]> = Vec::new();
+
+		for (public_key, signature) in &self.signatures {
+			if !authorized.contains(public_key) || seen.contains(public_key) {
+				continue;
+			}
+
+			let public_key_parsed = match PublicKey::from_bytes(public_key) {
+				Ok(public_key) => public_key,
+				Err(_) => continue,
+			};
+
+			let signature = match Signature::try_from(*signature) {
+				Ok(signature) => signature,
+				Err(_) => continue,
+			};
+
+			if public_key_parsed.verify(message, &signature).is_ok() {
+				seen.push(*public_key);
+			}
+		}
+
+		seen.len()
+	}
 }
 
 /// An enum to handle errors generated while validating `Transaction`s.
@@ -141,6 +271,7 @@ pub enum ValidationError {
 	WrongPassword,
 	InvalidSignature,
 	InvalidAmount,
+	NotUnlocked,
 }
 
 impl fmt::Display for ValidationError {
@@ -149,9 +280,147 @@ impl fmt::Display for ValidationError {
         	Self::Tempered =>  write!(f, "Tempered transaction."),
         	Self::WrongPassword => write!(f, "Wrong password."),
         	Self::InvalidSignature => write!(f, "Invalid signature."),
-		Self::InvalidAmount => write!(f, "Invalid amount.")
+		Self::InvalidAmount => write!(f, "Invalid amount."),
+		Self::NotUnlocked => write!(f, "The account is locked or the unlock has expired.")
         }
     }
 }
 
 impl error::Error for ValidationError {}
+
+/// A crate-wide error enum wrapping the fallible operations performed while
+/// building an `UnverifiedTransaction`.
+///
+/// It unifies the `ed25519_dalek` signing errors, the SHA-512 slice-conversion
+/// failures and the `ValidationError`s, so that every crypto path can be
+/// propagated with `?` instead of panicking through `.expect(...)`.
+#[derive(Debug)]
+pub enum CryptoError {
+	Signature(SignatureError),
+	Hash(TryFromSliceError),
+	Validation(ValidationError),
+}
+
+impl fmt::Display for CryptoError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Signature(error) => write!(f, "Signing error: {}", error),
+			Self::Hash(error) => write!(f, "Hashing error: {}", error),
+			Self::Validation(error) => write!(f, "Validation error: {}", error),
+		}
+	}
+}
+
+impl error::Error for CryptoError {}
+
+impl From<SignatureError> for CryptoError {
+	fn from(error: SignatureError) -> Self {
+		Self::Signature(error)
+	}
+}
+
+impl From<TryFromSliceError> for CryptoError {
+	fn from(error: TryFromSliceError) -> Self {
+		Self::Hash(error)
+	}
+}
+
+impl From<ValidationError> for CryptoError {
+	fn from(error: ValidationError) -> Self {
+		Self::Validation(error)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Builds a funded account whose password matches its name, so it can sign
+	/// and be validated in the tests.
+	fn account(name: &str, balance: f64) -> Account {
+		let mut account = Account::new(name, name, name);
+		account.add_money(balance);
+		account
+	}
+
+	#[test]
+	fn a_transaction_validates_against_its_own_chain_id() {
+		let mut sender = account("sender", 100.0);
+		let receiver = account("receiver", 0.0);
+
+		let authorized = [sender.public_key()];
+		let transaction = UnverifiedTransaction::new(sender, receiver, 10.0, "sender", 1).unwrap();
+		let hash = transaction.hash;
+
+		assert!(transaction.validate(hash, 1, &authorized, 1).is_ok());
+	}
+
+	#[test]
+	fn a_transaction_minted_for_another_chain_id_is_rejected() {
+		let mut sender = account("sender", 100.0);
+		let receiver = account("receiver", 0.0);
+
+		// Signed for chain 1 but presented to chain 2: the recomputed message no
+		// longer matches the signature, preventing a cross-chain replay.
+		let authorized = [sender.public_key()];
+		let transaction = UnverifiedTransaction::new(sender, receiver, 10.0, "sender", 1).unwrap();
+		let hash = transaction.hash;
+
+		assert!(matches!(transaction.validate(hash, 2, &authorized, 1), Err(ValidationError::InvalidSignature)));
+	}
+
+	#[test]
+	fn multisig_accepts_when_the_threshold_is_met() {
+		let mut sender = account("sender", 100.0);
+		let mut cosigner = account("cosigner", 0.0);
+		let receiver = account("receiver", 0.0);
+
+		let authorized = [sender.public_key(), cosigner.public_key()];
+		let transaction = UnverifiedTransaction::new_multisig(&[&mut sender, &mut cosigner], receiver, 10.0, "sender", 1).unwrap();
+		let hash = transaction.hash;
+
+		assert!(transaction.validate(hash, 1, &authorized, 2).is_ok());
+	}
+
+	#[test]
+	fn multisig_accepts_a_threshold_below_the_signer_count() {
+		let mut sender = account("sender", 100.0);
+		let mut second = account("second", 0.0);
+		let mut third = account("third", 0.0);
+		let receiver = account("receiver", 0.0);
+
+		// A 2-of-3: three authorized signers, only two required.
+		let authorized = [sender.public_key(), second.public_key(), third.public_key()];
+		let transaction = UnverifiedTransaction::new_multisig(&[&mut sender, &mut second, &mut third], receiver, 10.0, "sender", 1).unwrap();
+		let hash = transaction.hash;
+
+		assert!(transaction.validate(hash, 1, &authorized, 2).is_ok());
+	}
+
+	#[test]
+	fn multisig_rejects_fewer_signatures_than_the_threshold() {
+		let mut sender = account("sender", 100.0);
+		let receiver = account("receiver", 0.0);
+
+		let authorized = [sender.public_key()];
+		let transaction = UnverifiedTransaction::new_multisig(&[&mut sender], receiver, 10.0, "sender", 1).unwrap();
+		let hash = transaction.hash;
+
+		assert!(matches!(transaction.validate(hash, 1, &authorized, 2), Err(ValidationError::InvalidSignature)));
+	}
+
+	#[test]
+	fn multisig_ignores_signatures_from_unauthorized_keys() {
+		let mut sender = account("sender", 100.0);
+		let mut outsider = account("outsider", 0.0);
+		let receiver = account("receiver", 0.0);
+
+		// The outsider signs, but only the sender is authorized, so its valid
+		// signature must not count towards the threshold.
+		let authorized = [sender.public_key()];
+		let transaction = UnverifiedTransaction::new_multisig(&[&mut sender, &mut outsider], receiver, 10.0, "sender", 1).unwrap();
+		let hash = transaction.hash;
+
+		assert!(matches!(transaction.validate(hash, 1, &authorized, 2), Err(ValidationError::InvalidSignature)));
+	}
+}